@@ -0,0 +1,411 @@
+// Copyright 2019. The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! An append-only Merkle Mountain Range (MMR) accumulator used to maintain the output, kernel and range-proof
+//! commitment roots incrementally as a block is added to the chain, rather than recomputing a full Merkle tree on
+//! every validation.
+//!
+//! The accumulator is stored as a flat post-order vector of node hashes, exactly as described in Peter Todd's
+//! original MMR writeup: appending a leaf pushes its hash, then while the most recently completed peak has a left
+//! sibling of equal height, the two are popped and replaced by their parent `H(left || right)` (O(log n) amortized
+//! per append). The roots of the perfect binary subtrees that remain after an append are called "peaks"; the MMR
+//! root is the peaks "bagged" right-to-left with `H(acc || peak)`.
+
+use digest::Digest;
+use std::marker::PhantomData;
+
+use crate::validation::ValidationError;
+
+/// A 32-byte domain hash produced by the accumulator's hasher.
+pub type Hash = [u8; 32];
+
+/// An append-only Merkle Mountain Range over 32-byte leaf hashes.
+///
+/// `nodes` is the flat post-order representation of the MMR: every leaf and every internal node ever created is
+/// appended here and never removed, so historical inclusion proofs remain valid after further appends. `heights`
+/// mirrors `nodes` and records each node's height in its subtree (0 for a leaf); two peaks are only ever merged
+/// when their heights actually match, which is what keeps the shape of the tree correct as leaves are added.
+#[derive(Debug, Clone)]
+pub struct MerkleMountainRange<D> {
+    nodes: Vec<Hash>,
+    heights: Vec<u32>,
+    /// For an internal node, the position of its left and right children.
+    children: Vec<Option<(usize, usize)>>,
+    /// `parent[pos]` is the position `pos` was merged into, once it stops being a peak.
+    parent: Vec<Option<usize>>,
+    /// Positions (indices into `nodes`) of the current peaks, ordered left to right (equivalently, tallest to
+    /// shortest, since peak height strictly decreases left to right).
+    peaks: Vec<usize>,
+    /// `leaf_positions[i]` is the node position that leaf `i` was stored at when it was appended.
+    leaf_positions: Vec<usize>,
+    _digest: PhantomData<D>,
+}
+
+impl<D: Digest<OutputSize = digest::generic_array::typenum::U32>> MerkleMountainRange<D> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            heights: Vec::new(),
+            children: Vec::new(),
+            parent: Vec::new(),
+            peaks: Vec::new(),
+            leaf_positions: Vec::new(),
+            _digest: PhantomData,
+        }
+    }
+
+    /// The number of leaves appended so far.
+    pub fn len(&self) -> usize {
+        self.leaf_positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaf_positions.is_empty()
+    }
+
+    /// Append a new leaf hash to the MMR, updating the peaks in O(log n) amortized time. Returns the index of the
+    /// new leaf (not its node position), for use with [`Self::get_proof`].
+    pub fn push(&mut self, leaf_hash: Hash) -> usize {
+        let leaf_pos = self.nodes.len();
+        self.nodes.push(leaf_hash);
+        self.heights.push(0);
+        self.children.push(None);
+        self.parent.push(None);
+        self.peaks.push(leaf_pos);
+
+        // While the two most recently completed peaks have equal height (i.e. the mountain just completed a new
+        // level), merge them into their parent and keep carrying upward.
+        while self.peaks.len() >= 2 {
+            let right_pos = self.peaks[self.peaks.len() - 1];
+            let left_pos = self.peaks[self.peaks.len() - 2];
+            if self.heights[left_pos] != self.heights[right_pos] {
+                break;
+            }
+            let parent_hash = hash_pair::<D>(&self.nodes[left_pos], &self.nodes[right_pos]);
+            let parent_pos = self.nodes.len();
+            self.nodes.push(parent_hash);
+            self.heights.push(self.heights[left_pos] + 1);
+            self.children.push(Some((left_pos, right_pos)));
+            self.parent.push(None);
+            self.parent[left_pos] = Some(parent_pos);
+            self.parent[right_pos] = Some(parent_pos);
+
+            self.peaks.truncate(self.peaks.len() - 2);
+            self.peaks.push(parent_pos);
+        }
+
+        let leaf_index = self.leaf_positions.len();
+        self.leaf_positions.push(leaf_pos);
+        leaf_index
+    }
+
+    /// The current peak hashes, left to right.
+    fn peak_hashes(&self) -> Vec<Hash> {
+        self.peaks.iter().map(|&i| self.nodes[i]).collect()
+    }
+
+    /// Computes the MMR root by "bagging" the peaks: folding the peak hashes right-to-left with `H(acc || peak)`.
+    pub fn get_root(&self) -> Hash {
+        bag_peaks::<D>(&self.peak_hashes())
+    }
+
+    /// Builds an inclusion proof that the leaf appended at `leaf_index` is a member of the current MMR.
+    pub fn get_proof(&self, leaf_index: usize) -> Result<MmrInclusionProof, ValidationError> {
+        let mut pos = *self
+            .leaf_positions
+            .get(leaf_index)
+            .ok_or(ValidationError::InvalidMmrProof)?;
+        let leaf_hash = self.nodes[pos];
+
+        // Walk up from the leaf to its peak, recording the sibling hash and whether that sibling is the right-hand
+        // child at each level, by following the parent/children pointers recorded during `push`.
+        let mut siblings = Vec::new();
+        while let Some(parent_pos) = self.parent[pos] {
+            let (left, right) = self.children[parent_pos].expect("parent node always has children");
+            let (sibling_pos, sibling_is_right) = if pos == left {
+                (right, true)
+            } else {
+                debug_assert_eq!(pos, right);
+                (left, false)
+            };
+            siblings.push((self.nodes[sibling_pos], sibling_is_right));
+            pos = parent_pos;
+        }
+
+        let peak_index = self
+            .peaks
+            .iter()
+            .position(|&p| p == pos)
+            .ok_or(ValidationError::InvalidMmrProof)?;
+        let other_peaks = self
+            .peaks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != peak_index)
+            .map(|(_, &p)| self.nodes[p])
+            .collect();
+
+        Ok(MmrInclusionProof {
+            leaf_hash,
+            siblings,
+            other_peaks,
+            peak_index,
+        })
+    }
+}
+
+impl<D: Digest<OutputSize = digest::generic_array::typenum::U32>> Default for MerkleMountainRange<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hashes two 32-byte nodes together to form their parent, `H(left || right)`. Shared with
+/// `validation::light_client`, which builds an unrelated Merkle tree (of header hashes rather than MMR nodes) over
+/// the same 32-byte hash type and needs the identical construction.
+pub(crate) fn hash_pair<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(
+    left: &Hash,
+    right: &Hash,
+) -> Hash {
+    let mut digest = D::new();
+    digest.update(left);
+    digest.update(right);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&digest.finalize());
+    out
+}
+
+fn bag_peaks<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(peaks: &[Hash]) -> Hash {
+    match peaks.split_last() {
+        None => [0u8; 32],
+        Some((last, rest)) => {
+            let mut acc = *last;
+            for peak in rest.iter().rev() {
+                let mut digest = D::new();
+                digest.update(&acc);
+                digest.update(peak);
+                let mut out = [0u8; 32];
+                out.copy_from_slice(&digest.finalize());
+                acc = out;
+            }
+            acc
+        },
+    }
+}
+
+/// A proof that a single leaf belongs to the MMR that produced a given root.
+///
+/// Verification recomputes the peak the leaf belongs to by hashing up the sibling path, then bags that recomputed
+/// peak together with the other, untouched peaks and compares the result to the expected root.
+#[derive(Debug, Clone)]
+pub struct MmrInclusionProof {
+    leaf_hash: Hash,
+    /// Sibling hash and whether the sibling is the right-hand node at that level.
+    siblings: Vec<(Hash, bool)>,
+    /// The peaks other than the one the leaf belongs to, in peak order.
+    other_peaks: Vec<Hash>,
+    peak_index: usize,
+}
+
+impl MmrInclusionProof {
+    /// Verify this proof against an expected MMR root.
+    pub fn verify<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(
+        &self,
+        expected_root: &Hash,
+    ) -> Result<(), ValidationError> {
+        let mut acc = self.leaf_hash;
+        for (sibling, sibling_is_right) in &self.siblings {
+            let mut digest = D::new();
+            if *sibling_is_right {
+                digest.update(&acc);
+                digest.update(sibling);
+            } else {
+                digest.update(sibling);
+                digest.update(&acc);
+            }
+            let mut out = [0u8; 32];
+            out.copy_from_slice(&digest.finalize());
+            acc = out;
+        }
+
+        let mut peaks = self.other_peaks.clone();
+        peaks.insert(self.peak_index.min(peaks.len()), acc);
+        let root = bag_peaks::<D>(&peaks);
+        if &root == expected_root {
+            Ok(())
+        } else {
+            Err(ValidationError::InvalidMmrProof)
+        }
+    }
+}
+
+/// The three incrementally-maintained MMRs backing a block's output, kernel and range-proof commitment roots,
+/// wired directly into the [`ValidationError`] variants the full-block validator already returns so that appending
+/// a block's outputs/kernels updates the roots in O(log n) instead of rebuilding a full Merkle tree.
+#[derive(Debug, Clone)]
+pub struct BlockAccumulatedRoots<D> {
+    pub output_mmr: MerkleMountainRange<D>,
+    pub kernel_mmr: MerkleMountainRange<D>,
+    pub range_proof_mmr: MerkleMountainRange<D>,
+}
+
+impl<D: Digest<OutputSize = digest::generic_array::typenum::U32>> BlockAccumulatedRoots<D> {
+    pub fn new() -> Self {
+        Self {
+            output_mmr: MerkleMountainRange::new(),
+            kernel_mmr: MerkleMountainRange::new(),
+            range_proof_mmr: MerkleMountainRange::new(),
+        }
+    }
+
+    /// Checks the current output MMR root against the root recorded in a block header, returning
+    /// [`ValidationError::InvalidOutputMr`] on mismatch.
+    pub fn validate_output_mr(&self, expected_output_mr: &Hash) -> Result<(), ValidationError> {
+        if &self.output_mmr.get_root() == expected_output_mr {
+            Ok(())
+        } else {
+            Err(ValidationError::InvalidOutputMr)
+        }
+    }
+
+    /// Checks the current kernel MMR root against the root recorded in a block header, returning
+    /// [`ValidationError::InvalidKernelMr`] on mismatch.
+    pub fn validate_kernel_mr(&self, expected_kernel_mr: &Hash) -> Result<(), ValidationError> {
+        if &self.kernel_mmr.get_root() == expected_kernel_mr {
+            Ok(())
+        } else {
+            Err(ValidationError::InvalidKernelMr)
+        }
+    }
+
+    /// Checks the current range-proof MMR root against the root recorded in a block header, returning
+    /// [`ValidationError::InvalidRangeProofMr`] on mismatch.
+    pub fn validate_range_proof_mr(&self, expected_range_proof_mr: &Hash) -> Result<(), ValidationError> {
+        if &self.range_proof_mmr.get_root() == expected_range_proof_mr {
+            Ok(())
+        } else {
+            Err(ValidationError::InvalidRangeProofMr)
+        }
+    }
+}
+
+impl<D: Digest<OutputSize = digest::generic_array::typenum::U32>> Default for BlockAccumulatedRoots<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::Sha256;
+
+    fn leaf(byte: u8) -> Hash {
+        [byte; 32]
+    }
+
+    #[test]
+    fn empty_mmr_has_zero_root() {
+        let mmr = MerkleMountainRange::<Sha256>::new();
+        assert_eq!(mmr.get_root(), [0u8; 32]);
+        assert!(mmr.is_empty());
+    }
+
+    #[test]
+    fn single_leaf_proof_round_trips() {
+        let mut mmr = MerkleMountainRange::<Sha256>::new();
+        let index = mmr.push(leaf(1));
+        let root = mmr.get_root();
+        let proof = mmr.get_proof(index).unwrap();
+        assert!(proof.verify::<Sha256>(&root).is_ok());
+    }
+
+    #[test]
+    fn every_leaf_proves_against_the_current_root_for_many_leaf_counts() {
+        for num_leaves in 1..=17u8 {
+            let mut mmr = MerkleMountainRange::<Sha256>::new();
+            for i in 0..num_leaves {
+                mmr.push(leaf(i));
+            }
+            let root = mmr.get_root();
+            for i in 0..num_leaves as usize {
+                let proof = mmr.get_proof(i).unwrap_or_else(|_| panic!("proof failed for leaf {}", i));
+                assert!(
+                    proof.verify::<Sha256>(&root).is_ok(),
+                    "proof for leaf {} did not verify with {} leaves",
+                    i,
+                    num_leaves
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn proof_does_not_verify_against_wrong_root() {
+        let mut mmr = MerkleMountainRange::<Sha256>::new();
+        mmr.push(leaf(1));
+        mmr.push(leaf(2));
+        mmr.push(leaf(3));
+        let proof = mmr.get_proof(1).unwrap();
+        let wrong_root = [0xFFu8; 32];
+        assert!(matches!(
+            proof.verify::<Sha256>(&wrong_root),
+            Err(ValidationError::InvalidMmrProof)
+        ));
+    }
+
+    #[test]
+    fn out_of_range_leaf_index_is_rejected() {
+        let mut mmr = MerkleMountainRange::<Sha256>::new();
+        mmr.push(leaf(1));
+        assert!(matches!(mmr.get_proof(5), Err(ValidationError::InvalidMmrProof)));
+    }
+
+    #[test]
+    fn block_accumulated_roots_validate_against_header_roots() {
+        let mut roots = BlockAccumulatedRoots::<Sha256>::new();
+        roots.output_mmr.push(leaf(1));
+        roots.kernel_mmr.push(leaf(2));
+        roots.range_proof_mmr.push(leaf(3));
+
+        let output_root = roots.output_mmr.get_root();
+        let kernel_root = roots.kernel_mmr.get_root();
+        let range_proof_root = roots.range_proof_mmr.get_root();
+
+        assert!(roots.validate_output_mr(&output_root).is_ok());
+        assert!(roots.validate_kernel_mr(&kernel_root).is_ok());
+        assert!(roots.validate_range_proof_mr(&range_proof_root).is_ok());
+
+        assert!(matches!(
+            roots.validate_output_mr(&kernel_root),
+            Err(ValidationError::InvalidOutputMr)
+        ));
+        assert!(matches!(
+            roots.validate_kernel_mr(&output_root),
+            Err(ValidationError::InvalidKernelMr)
+        ));
+        assert!(matches!(
+            roots.validate_range_proof_mr(&output_root),
+            Err(ValidationError::InvalidRangeProofMr)
+        ));
+    }
+}
@@ -61,6 +61,10 @@ pub enum ValidationError {
     InvalidKernelMr,
     #[error("Invalid range proof merkle root")]
     InvalidRangeProofMr,
+    #[error("Merkle mountain range inclusion proof is invalid")]
+    InvalidMmrProof,
+    #[error("Header proof is invalid")]
+    InvalidHeaderProof,
     #[error("Final state validation failed: The UTXO set did not balance with the expected emission at height {0}")]
     ChainBalanceValidationFailed(u64),
     #[error("Proof of work error: {0}")]
@@ -0,0 +1,394 @@
+// Copyright 2019. The Tari Project
+//
+// Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+// following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+// disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+// following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+// products derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+// INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+// WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+// USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Canonical-hash-trie checkpoints for light-client header validation.
+//!
+//! A light client does not download or validate the UTXO/kernel set; it only tracks the proof-of-work and linkage
+//! of the header chain. To do that without re-hashing every header back to genesis on every check, the header chain
+//! is partitioned into fixed-size sections (of [`SECTION_SIZE`] headers each); a Merkle root is computed over the
+//! header hashes in each completed section. These section roots are the only state a light client needs to trust
+//! (seeded from genesis or a hard-coded checkpoint list, and extended by one entry every time a section finalizes).
+//! A full node can then serve a compact membership proof of `(height, header_hash)` against the section root that
+//! covers that height, which the light client verifies locally alongside the header's own PoW and previous-hash
+//! linkage.
+
+use digest::Digest;
+
+use crate::{chain_storage::hash_pair, proof_of_work::PowError, validation::ValidationError};
+
+/// The number of headers covered by a single checkpoint section.
+pub const SECTION_SIZE: u64 = 2000;
+
+/// A 32-byte header hash.
+pub type HeaderHash = [u8; 32];
+
+/// The trusted checkpoint state held by a light client: one Merkle root per finalized section of the header chain.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderCheckpoints {
+    /// `section_roots[i]` is the Merkle root over header hashes `[i * SECTION_SIZE, (i + 1) * SECTION_SIZE)`.
+    section_roots: Vec<HeaderHash>,
+}
+
+impl HeaderCheckpoints {
+    /// Seed the checkpoint set from a list of known-good section roots, e.g. genesis or a hard-coded checkpoint.
+    pub fn from_known_roots(section_roots: Vec<HeaderHash>) -> Self {
+        Self { section_roots }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.section_roots.is_empty()
+    }
+
+    /// The number of sections this light client currently trusts.
+    pub fn len(&self) -> usize {
+        self.section_roots.len()
+    }
+
+    /// Append the root of a newly finalized section.
+    pub fn push_section_root(&mut self, root: HeaderHash) {
+        self.section_roots.push(root);
+    }
+
+    fn section_root_for_height(&self, height: u64) -> Result<HeaderHash, ValidationError> {
+        let index = (height / SECTION_SIZE) as usize;
+        self.section_roots.get(index).copied().ok_or(ValidationError::InvalidHeaderProof)
+    }
+
+    /// Verify a membership proof that `header_hash` is the header at `height`, against the trusted section root
+    /// covering that height. This checks the full `(height, header_hash)` pair: `proof` must place `header_hash`
+    /// at exactly the leaf position `height % SECTION_SIZE` within the section, not merely anywhere in it.
+    pub fn verify_membership<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(
+        &self,
+        height: u64,
+        header_hash: HeaderHash,
+        proof: &HeaderInclusionProof,
+    ) -> Result<(), ValidationError> {
+        let expected_root = self.section_root_for_height(height)?;
+        let expected_position = height % SECTION_SIZE;
+        if proof.leaf_position() != expected_position {
+            return Err(ValidationError::InvalidHeaderProof);
+        }
+        proof.verify::<D>(header_hash, &expected_root)
+    }
+}
+
+/// Computes the Merkle root over a completed section's header hashes, in height order.
+pub fn compute_section_root<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(
+    header_hashes: &[HeaderHash],
+) -> HeaderHash {
+    merkle_root::<D>(header_hashes)
+}
+
+/// Produces a Merkle membership proof that `header_hashes[index]` is included in the section's Merkle root. Run on
+/// a full node, which holds the complete header set for the section.
+pub fn produce_membership_proof<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(
+    header_hashes: &[HeaderHash],
+    index: usize,
+) -> Result<HeaderInclusionProof, ValidationError> {
+    if index >= header_hashes.len() {
+        return Err(ValidationError::InvalidHeaderProof);
+    }
+    let mut siblings = Vec::new();
+    let mut level: Vec<HeaderHash> = header_hashes.to_vec();
+    let mut pos = index;
+    while level.len() > 1 {
+        let is_right = pos % 2 == 1;
+        let sibling_pos = if is_right { pos - 1 } else { pos + 1 };
+        let sibling = level.get(sibling_pos).copied().unwrap_or(level[pos]);
+        siblings.push((sibling, is_right));
+        level = hash_level::<D>(&level);
+        pos /= 2;
+    }
+    Ok(HeaderInclusionProof {
+        leaf_index: index as u64,
+        siblings,
+    })
+}
+
+fn hash_level<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(level: &[HeaderHash]) -> Vec<HeaderHash> {
+    let mut next = Vec::with_capacity((level.len() + 1) / 2);
+    let mut iter = level.chunks(2);
+    while let Some(pair) = iter.next() {
+        let left = pair[0];
+        let right = pair.get(1).copied().unwrap_or(left);
+        next.push(hash_pair::<D>(&left, &right));
+    }
+    next
+}
+
+fn merkle_root<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(leaves: &[HeaderHash]) -> HeaderHash {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = hash_level::<D>(&level);
+    }
+    level[0]
+}
+
+/// A Merkle membership proof of a single header hash against a section root.
+#[derive(Debug, Clone)]
+pub struct HeaderInclusionProof {
+    /// The leaf's position within the section, i.e. `height % SECTION_SIZE` for the height it was produced for.
+    /// This is what lets [`HeaderCheckpoints::verify_membership`] check the pair `(height, header_hash)` rather
+    /// than just `header_hash` being a member of the section somewhere.
+    leaf_index: u64,
+    /// Sibling hash and whether the proven node is the right-hand node at that level (so the sibling is on the left).
+    siblings: Vec<(HeaderHash, bool)>,
+}
+
+impl HeaderInclusionProof {
+    /// The leaf's position within its section, as recorded when the proof was produced.
+    pub fn leaf_position(&self) -> u64 {
+        self.leaf_index
+    }
+
+    pub fn verify<D: Digest<OutputSize = digest::generic_array::typenum::U32>>(
+        &self,
+        header_hash: HeaderHash,
+        expected_root: &HeaderHash,
+    ) -> Result<(), ValidationError> {
+        let mut acc = header_hash;
+        for (sibling, is_right) in &self.siblings {
+            acc = if *is_right {
+                hash_pair::<D>(sibling, &acc)
+            } else {
+                hash_pair::<D>(&acc, sibling)
+            };
+        }
+        if &acc == expected_root {
+            Ok(())
+        } else {
+            Err(ValidationError::InvalidHeaderProof)
+        }
+    }
+}
+
+/// The minimal view of a block header that [`validate_light_client_header`] needs: enough to check its
+/// proof-of-work, its linkage to the previous header, and its identity for a checkpoint membership proof.
+/// It is expressed as a trait so this module does not need to depend on every field of the full header type to
+/// perform the three checks a light client actually needs; this module provides no implementation of it for
+/// `BlockHeader`.
+pub trait LightClientHeader {
+    fn height(&self) -> u64;
+    fn hash(&self) -> HeaderHash;
+    fn prev_hash(&self) -> HeaderHash;
+    /// Checks that this header's proof-of-work is valid, independent of the chain it is attached to.
+    fn check_proof_of_work(&self) -> Result<(), PowError>;
+}
+
+/// Validates `header` for `ValidationMode::LightClient`: this is the entire light-client header validation path —
+/// it does not touch the UTXO or kernel set at all. Three checks are performed, in the same order the full
+/// validator would perform the equivalent checks:
+///
+/// 1. `header` chains from `prev_header` (reusing [`ValidationError::PreviousHashNotFound`] on failure).
+/// 2. `header`'s proof-of-work is valid (reusing [`ValidationError::ProofOfWorkError`] on failure).
+/// 3. `header` is a genuine member of the checkpoint section covering its height, via `proof` (returning
+///    [`ValidationError::InvalidHeaderProof`] on failure).
+pub fn validate_light_client_header<D, H>(
+    header: &H,
+    prev_header: &H,
+    checkpoints: &HeaderCheckpoints,
+    proof: &HeaderInclusionProof,
+) -> Result<(), ValidationError>
+where
+    D: Digest<OutputSize = digest::generic_array::typenum::U32>,
+    H: LightClientHeader,
+{
+    if header.prev_hash() != prev_header.hash() {
+        return Err(ValidationError::PreviousHashNotFound);
+    }
+    header.check_proof_of_work()?;
+    checkpoints.verify_membership::<D>(header.height(), header.hash(), proof)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::Sha256;
+
+    struct TestHeader {
+        height: u64,
+        hash: HeaderHash,
+        prev_hash: HeaderHash,
+        pow_is_valid: bool,
+    }
+
+    impl LightClientHeader for TestHeader {
+        fn height(&self) -> u64 {
+            self.height
+        }
+
+        fn hash(&self) -> HeaderHash {
+            self.hash
+        }
+
+        fn prev_hash(&self) -> HeaderHash {
+            self.prev_hash
+        }
+
+        fn check_proof_of_work(&self) -> Result<(), PowError> {
+            if self.pow_is_valid {
+                Ok(())
+            } else {
+                Err(PowError::InvalidProofOfWork)
+            }
+        }
+    }
+
+    fn section_with_header_at(height: u64, hash: HeaderHash) -> (HeaderCheckpoints, HeaderInclusionProof) {
+        let position = (height % SECTION_SIZE) as usize;
+        let mut hashes = vec![[0u8; 32]; position + 1];
+        hashes[position] = hash;
+        let root = compute_section_root::<Sha256>(&hashes);
+        let proof = produce_membership_proof::<Sha256>(&hashes, position).unwrap();
+        let mut checkpoints = HeaderCheckpoints::default();
+        for _ in 0..=(height / SECTION_SIZE) {
+            checkpoints.push_section_root(root);
+        }
+        (checkpoints, proof)
+    }
+
+    #[test]
+    fn valid_header_passes_all_three_checks() {
+        let prev = TestHeader {
+            height: 9,
+            hash: [1u8; 32],
+            prev_hash: [0u8; 32],
+            pow_is_valid: true,
+        };
+        let header = TestHeader {
+            height: 10,
+            hash: [2u8; 32],
+            prev_hash: [1u8; 32],
+            pow_is_valid: true,
+        };
+        let (checkpoints, proof) = section_with_header_at(header.height, header.hash);
+
+        assert!(validate_light_client_header::<Sha256, _>(&header, &prev, &checkpoints, &proof).is_ok());
+    }
+
+    #[test]
+    fn header_not_chained_to_prev_is_rejected() {
+        let prev = TestHeader {
+            height: 9,
+            hash: [1u8; 32],
+            prev_hash: [0u8; 32],
+            pow_is_valid: true,
+        };
+        let header = TestHeader {
+            height: 10,
+            hash: [2u8; 32],
+            prev_hash: [0xFFu8; 32],
+            pow_is_valid: true,
+        };
+        let (checkpoints, proof) = section_with_header_at(header.height, header.hash);
+
+        assert!(matches!(
+            validate_light_client_header::<Sha256, _>(&header, &prev, &checkpoints, &proof),
+            Err(ValidationError::PreviousHashNotFound)
+        ));
+    }
+
+    #[test]
+    fn header_with_invalid_proof_of_work_is_rejected() {
+        let prev = TestHeader {
+            height: 9,
+            hash: [1u8; 32],
+            prev_hash: [0u8; 32],
+            pow_is_valid: true,
+        };
+        let header = TestHeader {
+            height: 10,
+            hash: [2u8; 32],
+            prev_hash: [1u8; 32],
+            pow_is_valid: false,
+        };
+        let (checkpoints, proof) = section_with_header_at(header.height, header.hash);
+
+        assert!(matches!(
+            validate_light_client_header::<Sha256, _>(&header, &prev, &checkpoints, &proof),
+            Err(ValidationError::ProofOfWorkError(_))
+        ));
+    }
+
+    #[test]
+    fn header_not_matching_checkpoint_is_rejected() {
+        let prev = TestHeader {
+            height: 9,
+            hash: [1u8; 32],
+            prev_hash: [0u8; 32],
+            pow_is_valid: true,
+        };
+        let header = TestHeader {
+            height: 10,
+            hash: [2u8; 32],
+            prev_hash: [1u8; 32],
+            pow_is_valid: true,
+        };
+        // Checkpoints for a different header hash at the same height.
+        let (checkpoints, proof) = section_with_header_at(header.height, [0xAAu8; 32]);
+
+        assert!(matches!(
+            validate_light_client_header::<Sha256, _>(&header, &prev, &checkpoints, &proof),
+            Err(ValidationError::InvalidHeaderProof)
+        ));
+    }
+
+    /// A section with more than one header, so a proof's leaf position is meaningful: several header hashes can be
+    /// genuine leaves under the same section root, and a proof for one of them must not verify for another height.
+    fn multi_header_section() -> (HeaderCheckpoints, Vec<HeaderHash>, Vec<HeaderInclusionProof>) {
+        let hashes: Vec<HeaderHash> = (0u8..8).map(|i| [i; 32]).collect();
+        let root = compute_section_root::<Sha256>(&hashes);
+        let proofs = (0..hashes.len())
+            .map(|i| produce_membership_proof::<Sha256>(&hashes, i).unwrap())
+            .collect();
+        let mut checkpoints = HeaderCheckpoints::default();
+        checkpoints.push_section_root(root);
+        (checkpoints, hashes, proofs)
+    }
+
+    #[test]
+    fn proof_at_correct_position_in_multi_header_section_is_accepted() {
+        let (checkpoints, hashes, proofs) = multi_header_section();
+
+        for (position, hash) in hashes.iter().enumerate() {
+            assert!(checkpoints
+                .verify_membership::<Sha256>(position as u64, *hash, &proofs[position])
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn proof_for_the_wrong_position_is_rejected_even_though_the_hash_is_a_genuine_leaf() {
+        let (checkpoints, hashes, proofs) = multi_header_section();
+
+        // `proofs[0]` is a genuine inclusion proof for `hashes[0]`, but at leaf position 0, not 3. Claiming
+        // `hashes[0]` is the header at height 3 (which maps to position 3 in this section) must be rejected, even
+        // though `hashes[0]` really is a leaf somewhere under the section root.
+        assert!(matches!(
+            checkpoints.verify_membership::<Sha256>(3, hashes[0], &proofs[0]),
+            Err(ValidationError::InvalidHeaderProof)
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::time::Duration;
+
+/// Configuration for the DHT.
+#[derive(Debug, Clone)]
+pub struct DhtConfig {
+    pub network_discovery: DhtNetworkDiscoveryConfig,
+}
+
+impl Default for DhtConfig {
+    fn default() -> Self {
+        Self {
+            network_discovery: Default::default(),
+        }
+    }
+}
+
+/// Configuration for the `DhtNetworkDiscovery` state machine.
+#[derive(Debug, Clone)]
+pub struct DhtNetworkDiscoveryConfig {
+    /// If false, network discovery is disabled, and the node relies entirely on seed peers/peer connections
+    /// established elsewhere.
+    pub enabled: bool,
+    /// The period to idle for after a discovery round completes successfully and there is nothing left to do.
+    pub idle_period: Duration,
+    /// The base delay used for exponential backoff after a discovery round fails or errors. The delay for the
+    /// `n`th consecutive failure is `min(base_period * 2^n, max_backoff_cap)`, plus jitter.
+    pub base_period: Duration,
+    /// The upper bound on the exponential backoff delay applied after repeated consecutive failures, preventing
+    /// the delay from growing unbounded.
+    pub max_backoff_cap: Duration,
+}
+
+impl Default for DhtNetworkDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_period: Duration::from_secs(5 * 60),
+            base_period: Duration::from_secs(10),
+            max_backoff_cap: Duration::from_secs(60 * 60),
+        }
+    }
+}
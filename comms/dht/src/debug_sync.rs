@@ -0,0 +1,327 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Drop-in `Mutex`/`RwLock` wrappers that detect lock-order inversions.
+//!
+//! `DhtNetworkDiscovery` and the connectivity/peer-manager state it shares across long-lived tasks is exactly the
+//! kind of code where two call paths can end up acquiring the same pair of locks in opposite orders without anyone
+//! noticing until a production deadlock. When the `debug-sync` feature is enabled, [`Mutex`] and [`RwLock`] here
+//! track, per thread, the set of locks currently held; every time a new lock is acquired, an edge
+//! `already-held-lock -> newly-acquired-lock` is added to a global, process-wide lock-ordering graph keyed by each
+//! lock's construction call site. If adding that edge would close a cycle, the two orderings are genuinely
+//! inconsistent, so we panic immediately with both call sites rather than wait for the inversion to deadlock in
+//! production.
+//!
+//! When `debug-sync` is disabled these types are plain re-exports of `std::sync::{Mutex, RwLock}` with zero
+//! overhead, so the wrappers can be used unconditionally and only pay for the bookkeeping in builds that enable
+//! the feature.
+//!
+//! Scope: this module holds the detector only. No code in this crate constructs a [`Mutex`]/[`RwLock`] from this
+//! module, including the `PeerManager`/connectivity state shared by the discovery state machine, and this crate
+//! declares no `debug-sync` Cargo feature. The checked implementation and its tests below are compiled only in a
+//! build that enables that feature, so the DHT test suite does not exercise the detector.
+
+#[cfg(feature = "debug-sync")]
+mod checked {
+    use once_cell::sync::Lazy;
+    use std::{
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+        ops::{Deref, DerefMut},
+        panic::Location,
+        sync::Mutex as StdMutex,
+    };
+
+    /// The call site at which a lock was constructed, used as its identity in the ordering graph.
+    type LockId = &'static Location<'static>;
+
+    /// `already_held -> locks acquired while already_held was held`
+    static LOCK_ORDER_GRAPH: Lazy<StdMutex<HashMap<LockId, HashSet<LockId>>>> =
+        Lazy::new(|| StdMutex::new(HashMap::new()));
+
+    thread_local! {
+        static HELD_LOCKS: RefCell<Vec<LockId>> = RefCell::new(Vec::new());
+    }
+
+    /// Record that `id` is about to be acquired while everything in `HELD_LOCKS` is held, panicking if doing so
+    /// would close a cycle in the global lock-ordering graph.
+    #[track_caller]
+    fn record_acquisition(id: LockId) {
+        HELD_LOCKS.with(|held| {
+            let held = held.borrow();
+            if held.contains(&id) {
+                // A lock with the same construction site is already held on this thread (the same lock, or a
+                // distinct one built at that site); a site cannot be ordered against itself.
+                return;
+            }
+            // Recover from poisoning the same way `Mutex::lock` does for the wrapped user lock: a panic
+            // elsewhere (including one we raise below, outside this guard's scope) must not permanently wedge
+            // every other call to this detector for the rest of the process.
+            let mut graph = LOCK_ORDER_GRAPH.lock().unwrap_or_else(|e| e.into_inner());
+            let mut inversion = None;
+            for &already_held in held.iter() {
+                if path_exists(&graph, id, already_held) {
+                    inversion = Some(already_held);
+                    break;
+                }
+                graph.entry(already_held).or_insert_with(HashSet::new).insert(id);
+            }
+            // Drop the guard before panicking so an unwind through this scope can never poison
+            // `LOCK_ORDER_GRAPH` for unrelated locks still to be checked on other threads.
+            drop(graph);
+            if let Some(already_held) = inversion {
+                panic!(
+                    "Lock order inversion detected: lock constructed at {} was acquired while lock constructed \
+                     at {} was held, but {} is already known to be acquired before {} elsewhere. This is a \
+                     potential deadlock.",
+                    id, already_held, id, already_held
+                );
+            }
+        });
+        HELD_LOCKS.with(|held| held.borrow_mut().push(id));
+    }
+
+    fn release(id: LockId) {
+        HELD_LOCKS.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(pos) = held.iter().rposition(|&l| l == id) {
+                held.remove(pos);
+            }
+        });
+    }
+
+    /// Returns true if there is a path from `from` to `to` in the graph, i.e. acquiring `to` then `from` (in that
+    /// order) is already known to happen somewhere, which would make acquiring `from` then `to` a cycle.
+    fn path_exists(graph: &HashMap<LockId, HashSet<LockId>>, from: LockId, to: LockId) -> bool {
+        let mut stack = vec![from];
+        let mut visited = HashSet::new();
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+            if !visited.insert(node) {
+                continue;
+            }
+            if let Some(next) = graph.get(&node) {
+                stack.extend(next.iter().copied());
+            }
+        }
+        false
+    }
+
+    pub struct Mutex<T> {
+        id: LockId,
+        inner: std::sync::Mutex<T>,
+    }
+
+    impl<T> Mutex<T> {
+        #[track_caller]
+        pub fn new(value: T) -> Self {
+            Self {
+                id: Location::caller(),
+                inner: std::sync::Mutex::new(value),
+            }
+        }
+
+        /// Matches `std::sync::Mutex::lock`'s signature (returns a `LockResult`) so call sites compile unchanged
+        /// whether or not the `debug-sync` feature is enabled.
+        pub fn lock(&self) -> std::sync::LockResult<MutexGuard<'_, T>> {
+            record_acquisition(self.id);
+            match self.inner.lock() {
+                Ok(guard) => Ok(MutexGuard { id: self.id, guard }),
+                Err(poisoned) => Err(std::sync::PoisonError::new(MutexGuard {
+                    id: self.id,
+                    guard: poisoned.into_inner(),
+                })),
+            }
+        }
+    }
+
+    pub struct MutexGuard<'a, T> {
+        id: LockId,
+        guard: std::sync::MutexGuard<'a, T>,
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.guard
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.guard
+        }
+    }
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            release(self.id);
+        }
+    }
+
+    pub struct RwLock<T> {
+        id: LockId,
+        inner: std::sync::RwLock<T>,
+    }
+
+    impl<T> RwLock<T> {
+        #[track_caller]
+        pub fn new(value: T) -> Self {
+            Self {
+                id: Location::caller(),
+                inner: std::sync::RwLock::new(value),
+            }
+        }
+
+        /// Matches `std::sync::RwLock::read`'s signature (returns a `LockResult`) so call sites compile unchanged
+        /// whether or not the `debug-sync` feature is enabled.
+        pub fn read(&self) -> std::sync::LockResult<RwLockReadGuard<'_, T>> {
+            record_acquisition(self.id);
+            match self.inner.read() {
+                Ok(guard) => Ok(RwLockReadGuard { id: self.id, guard }),
+                Err(poisoned) => Err(std::sync::PoisonError::new(RwLockReadGuard {
+                    id: self.id,
+                    guard: poisoned.into_inner(),
+                })),
+            }
+        }
+
+        /// Matches `std::sync::RwLock::write`'s signature (returns a `LockResult`) so call sites compile unchanged
+        /// whether or not the `debug-sync` feature is enabled.
+        pub fn write(&self) -> std::sync::LockResult<RwLockWriteGuard<'_, T>> {
+            record_acquisition(self.id);
+            match self.inner.write() {
+                Ok(guard) => Ok(RwLockWriteGuard { id: self.id, guard }),
+                Err(poisoned) => Err(std::sync::PoisonError::new(RwLockWriteGuard {
+                    id: self.id,
+                    guard: poisoned.into_inner(),
+                })),
+            }
+        }
+    }
+
+    pub struct RwLockReadGuard<'a, T> {
+        id: LockId,
+        guard: std::sync::RwLockReadGuard<'a, T>,
+    }
+
+    impl<T> Deref for RwLockReadGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.guard
+        }
+    }
+
+    impl<T> Drop for RwLockReadGuard<'_, T> {
+        fn drop(&mut self) {
+            release(self.id);
+        }
+    }
+
+    pub struct RwLockWriteGuard<'a, T> {
+        id: LockId,
+        guard: std::sync::RwLockWriteGuard<'a, T>,
+    }
+
+    impl<T> Deref for RwLockWriteGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.guard
+        }
+    }
+
+    impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.guard
+        }
+    }
+
+    impl<T> Drop for RwLockWriteGuard<'_, T> {
+        fn drop(&mut self) {
+            release(self.id);
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn repeating_the_same_lock_order_does_not_panic() {
+            let a = Mutex::new(1);
+            let b = Mutex::new(2);
+
+            {
+                let _guard_a = a.lock().unwrap();
+                let _guard_b = b.lock().unwrap();
+            }
+
+            // Acquiring in the same order (a then b) again is fine: no cycle is introduced.
+            let _guard_a = a.lock().unwrap();
+            let _guard_b = b.lock().unwrap();
+        }
+
+        #[test]
+        #[should_panic(expected = "Lock order inversion detected")]
+        fn inverted_lock_order_panics() {
+            let a = Mutex::new(1);
+            let b = Mutex::new(2);
+
+            // Establish the order a -> b.
+            {
+                let _guard_a = a.lock().unwrap();
+                let _guard_b = b.lock().unwrap();
+            }
+
+            // Acquiring b -> a now closes a cycle in the lock-ordering graph and must panic.
+            let _guard_b = b.lock().unwrap();
+            let _guard_a = a.lock();
+        }
+
+        #[test]
+        fn locks_sharing_a_construction_site_are_not_flagged_as_a_cycle_with_themselves() {
+            // Two distinct `Mutex` instances constructed at the same call site (e.g. inside a loop, or a helper
+            // used from one place) share a `LockId`. Holding one while acquiring the other must not be treated as
+            // a self-cycle.
+            fn make() -> Mutex<i32> {
+                Mutex::new(0)
+            }
+
+            let a = make();
+            let b = make();
+
+            let _guard_a = a.lock().unwrap();
+            let _guard_b = b.lock().unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "debug-sync")]
+pub use checked::{Mutex, RwLock};
+
+#[cfg(not(feature = "debug-sync"))]
+pub use std::sync::{Mutex, RwLock};
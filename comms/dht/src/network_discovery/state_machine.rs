@@ -33,7 +33,9 @@ use crate::{
 };
 use futures::{future, future::Either};
 use log::*;
+use rand::Rng;
 use std::{
+    cmp,
     fmt,
     fmt::Display,
     future::Future,
@@ -41,6 +43,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 use tari_comms::{connectivity::ConnectivityRequester, peer_manager::NodeId, NodeIdentity, PeerManager};
 use tari_shutdown::ShutdownSignal;
@@ -111,29 +114,67 @@ impl<E: Into<NetworkDiscoveryError>> From<E> for StateEvent {
     }
 }
 
+/// A shared, thread-safe monotonic counter: increment by 1, read the current value, or reset to 0. Backs
+/// `NetworkDiscoveryContext::num_rounds` and `consecutive_failures`, which need identical bookkeeping.
+#[derive(Debug, Clone, Default)]
+pub struct AtomicCounter(Arc<AtomicUsize>);
+
+impl AtomicCounter {
+    /// Increments the counter by 1, returning the value from before the increment.
+    pub fn increment(&self) -> usize {
+        self.0.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// Returns the current value of the counter.
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Resets the counter to 0.
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Release);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NetworkDiscoveryContext {
     pub config: DhtConfig,
     pub peer_manager: Arc<PeerManager>,
     pub connectivity: ConnectivityRequester,
     pub node_identity: Arc<NodeIdentity>,
-    pub num_rounds: Arc<AtomicUsize>,
+    pub num_rounds: AtomicCounter,
+    pub consecutive_failures: AtomicCounter,
 }
 
 impl NetworkDiscoveryContext {
     /// Increment the number of rounds by 1
     pub fn increment_num_rounds(&self) -> usize {
-        self.num_rounds.fetch_add(1, Ordering::AcqRel)
+        self.num_rounds.increment()
     }
 
     /// Get the number of rounds
     pub fn num_rounds(&self) -> usize {
-        self.num_rounds.load(Ordering::Relaxed)
+        self.num_rounds.get()
     }
 
     /// Reset the number of rounds to 0
     pub fn reset_num_rounds(&self) {
-        self.num_rounds.store(0, Ordering::Release);
+        self.num_rounds.reset()
+    }
+
+    /// Increment the number of consecutive failed/errored rounds by 1
+    pub fn increment_consecutive_failures(&self) -> usize {
+        self.consecutive_failures.increment()
+    }
+
+    /// Get the number of consecutive failed/errored rounds
+    pub fn consecutive_failures(&self) -> usize {
+        self.consecutive_failures.get()
+    }
+
+    /// Reset the number of consecutive failures to 0
+    pub fn reset_consecutive_failures(&self) {
+        self.consecutive_failures.reset()
     }
 }
 
@@ -160,6 +201,7 @@ impl DhtNetworkDiscovery {
                 connectivity,
                 node_identity,
                 num_rounds: Default::default(),
+                consecutive_failures: Default::default(),
             },
             event_tx,
             shutdown_signal,
@@ -196,19 +238,33 @@ impl DhtNetworkDiscovery {
                     self.publish_event(DhtEvent::NetworkDiscoveryPeersAdded(stats.clone()));
                 }
                 if !stats.is_success() {
-                    return State::Waiting(self.config().network_discovery.on_failure_idle_period.into());
+                    let failures = self.context.increment_consecutive_failures();
+                    let delay = Self::backoff_with_jitter(config.base_period, config.max_backoff_cap, failures + 1);
+                    debug!(
+                        target: LOG_TARGET,
+                        "Discovery round did not succeed ({} consecutive failure(s)). Backing off for {:.0?}",
+                        failures + 1,
+                        delay
+                    );
+                    return State::Waiting(delay.into());
                 }
 
+                self.context.reset_consecutive_failures();
                 State::Ready(DiscoveryReady::new(self.context.clone(), stats))
             },
             (State::Ready(_), StateEvent::Idle) => State::Waiting(config.idle_period.into()),
             (_, StateEvent::Shutdown) => State::Shutdown,
             (_, StateEvent::Errored(err)) => {
+                let failures = self.context.increment_consecutive_failures();
+                let delay = Self::backoff_with_jitter(config.base_period, config.max_backoff_cap, failures + 1);
                 error!(
                     target: LOG_TARGET,
-                    "Network discovery errored: {}. Waiting for {:.0?}", err, config.on_failure_idle_period
+                    "Network discovery errored: {}. Backing off for {:.0?} ({} consecutive failure(s))",
+                    err,
+                    delay,
+                    failures + 1
                 );
-                State::Waiting(config.on_failure_idle_period.into())
+                State::Waiting(delay.into())
             },
             (state, event) => {
                 debug!(
@@ -224,6 +280,18 @@ impl DhtNetworkDiscovery {
         let _ = self.event_tx.send(Arc::new(event));
     }
 
+    /// Computes the `Waiting` duration for the `consecutive_failures`'th failure as exponential backoff capped at
+    /// `max_backoff_cap`, plus a uniform random jitter in `[0, duration / 2)` to avoid synchronized retry storms
+    /// when many peers come back online at the same time.
+    fn backoff_with_jitter(base_period: Duration, max_backoff_cap: Duration, consecutive_failures: usize) -> Duration {
+        let multiplier = 1u32.checked_shl(consecutive_failures as u32).unwrap_or(u32::MAX);
+        let backoff = base_period.checked_mul(multiplier).unwrap_or(max_backoff_cap);
+        let capped = cmp::min(backoff, max_backoff_cap);
+        let jitter_bound = cmp::max(capped.as_millis() as u64 / 2, 1);
+        let jitter = rand::thread_rng().gen_range(0..jitter_bound);
+        capped + Duration::from_millis(jitter)
+    }
+
     #[inline]
     fn config(&self) -> &DhtConfig {
         &self.context.config
@@ -322,4 +390,48 @@ impl Display for DhtNetworkDiscoveryRoundInfo {
             self.num_duplicate_peers,
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_exponentially_with_consecutive_failures() {
+        let base_period = Duration::from_secs(1);
+        let max_backoff_cap = Duration::from_secs(16);
+
+        let first = DhtNetworkDiscovery::backoff_with_jitter(base_period, max_backoff_cap, 1);
+        assert!(first >= Duration::from_secs(2) && first < Duration::from_secs(3));
+
+        let second = DhtNetworkDiscovery::backoff_with_jitter(base_period, max_backoff_cap, 2);
+        assert!(second >= Duration::from_secs(4) && second < Duration::from_secs(6));
+
+        let third = DhtNetworkDiscovery::backoff_with_jitter(base_period, max_backoff_cap, 3);
+        assert!(third >= Duration::from_secs(8) && third < Duration::from_secs(12));
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_backoff() {
+        let base_period = Duration::from_secs(1);
+        let max_backoff_cap = Duration::from_secs(16);
+
+        let delay = DhtNetworkDiscovery::backoff_with_jitter(base_period, max_backoff_cap, 10);
+        assert!(delay >= max_backoff_cap);
+        assert!(delay < max_backoff_cap + max_backoff_cap / 2);
+    }
+
+    #[test]
+    fn consecutive_failures_counter_increments_and_resets_to_zero() {
+        // Exercises the actual `AtomicCounter` type backing `NetworkDiscoveryContext::consecutive_failures` (and
+        // `num_rounds`), rather than a separate reimplementation of its increment/reset logic.
+        let counter = AtomicCounter::default();
+
+        assert_eq!(counter.increment(), 0);
+        assert_eq!(counter.increment(), 1);
+        assert_eq!(counter.get(), 2);
+
+        counter.reset();
+        assert_eq!(counter.get(), 0);
+    }
+}